@@ -0,0 +1,512 @@
+//! Elliptic Curve Verifiable Random Function (ECVRF) over the Ristretto
+//! group built on top of the Ed25519 curve (cipher suite `SUITE`, not yet
+//! standardized).  This crate is `no_std`-compatible when built without
+//! the default `std` feature, so it can be embedded in constrained and
+//! wasm consensus code; enable `std` (the default) for `OsRng`-backed
+//! convenience constructors, or `bin` to additionally build the `ecvrf`
+//! command-line front-end in `main.rs`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(non_snake_case, non_camel_case_types)]
+
+extern crate curve25519_dalek;
+extern crate ed25519_dalek;
+extern crate sha2;
+extern crate rand_core;
+extern crate zeroize;
+
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[macro_use]
+pub mod util;
+
+use util::Error as ECVRF_Error;
+
+use ed25519_dalek::SecretKey as ed25519_PrivateKey;
+
+use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
+use curve25519_dalek::scalar::Scalar as ed25519_Scalar;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+
+use zeroize::Zeroize;
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+pub const SUITE : u8 = 0x05;        /* cipher suite (not standardized yet).  This would be ECVRF-ED25519-SHA512-RistrettoElligator -- i.e. using the Ristretto group on ed25519 and its elligator function */
+
+/// A VRF secret key.  This owns the raw 32-byte seed and wipes it once it
+/// goes out of scope; the intermediate buffers derived from it (the
+/// expanded scalar, the nonce material) are wiped by the functions that
+/// derive them as soon as they are done with them, so long-term key
+/// material and per-call nonce secrets don't linger in freed memory.
+pub struct ECVRF_SecretKey {
+    seed: [u8; 32]
+}
+
+impl ECVRF_SecretKey {
+    pub fn new(seed: [u8; 32]) -> ECVRF_SecretKey {
+        ECVRF_SecretKey { seed }
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<ECVRF_SecretKey, ECVRF_Error> {
+        if bytes.len() != 32 {
+            return Err(ECVRF_Error::InvalidDataError);
+        }
+
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(bytes);
+        Ok(ECVRF_SecretKey { seed })
+    }
+
+    /// Generate a fresh secret key from a caller-supplied CSPRNG.  Taking
+    /// the RNG as a generic parameter (rather than reaching for `OsRng`
+    /// directly) is what lets this path work on `no_std` targets, which
+    /// have no OS to source entropy from.
+    pub fn generate<R: RngCore + CryptoRng>(csprng: &mut R) -> ECVRF_SecretKey {
+        let privkey = ed25519_PrivateKey::generate(csprng);
+        ECVRF_SecretKey { seed: privkey.to_bytes() }
+    }
+
+    /// Generate a fresh secret key using the OS's CSPRNG.  Only available
+    /// with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn generate_os_rng() -> ECVRF_SecretKey {
+        let mut csprng = rand::rngs::OsRng;
+        ECVRF_SecretKey::generate(&mut csprng)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Derive this secret key's public key.
+    pub fn public_key(&self) -> ECVRF_PublicKey {
+        let (pubkey, mut x_scalar, mut trunc_hash) = ECVRF_expand_privkey(self);
+        x_scalar.zeroize();
+        trunc_hash.zeroize();
+        pubkey
+    }
+}
+
+impl Drop for ECVRF_SecretKey {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+    }
+}
+
+/// A VRF public key: a point in the Ristretto group.  Ristretto's
+/// canonical encoding only ever decodes to a single representative of
+/// each prime-order-group element, so -- unlike raw Ed25519 points -- the
+/// only "weak" point that can decode at all is the identity, which is
+/// rejected outright below.  This sidesteps the need for the unsafe
+/// Edwards-to-Ristretto point conversion the CLI used to rely on.
+///
+/// NOTE: this is a breaking wire-format change from the original CLI,
+/// which treated a public key's 32 bytes as a compressed Ed25519/Edwards
+/// point and converted it to Ristretto at verification time.  A public
+/// key here is instead a *compressed Ristretto point* end to end, derived
+/// directly from the secret scalar (see `ECVRF_expand_privkey`) -- an
+/// existing Ed25519 identity key's raw bytes will either fail to decompress
+/// or decompress to a different, unrelated point, not the same key.
+/// Keys must be (re-)generated through `ECVRF_SecretKey::public_key` to be
+/// usable with this API.
+pub struct ECVRF_PublicKey {
+    point: RistrettoPoint
+}
+
+impl ECVRF_PublicKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<ECVRF_PublicKey, ECVRF_Error> {
+        if bytes.len() != 32 {
+            return Err(ECVRF_Error::InvalidDataError);
+        }
+
+        let point = CompressedRistretto::from_slice(bytes)
+            .decompress()
+            .ok_or(ECVRF_Error::InvalidDataError)?;
+
+        if point == RistrettoPoint::identity() {
+            return Err(ECVRF_Error::InvalidDataError);
+        }
+
+        Ok(ECVRF_PublicKey { point })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.point.compress().to_bytes()
+    }
+}
+
+pub struct ECVRF_Proof {
+    pub Gamma: RistrettoPoint,
+    pub c: ed25519_Scalar,
+    pub s: ed25519_Scalar
+}
+
+impl ECVRF_Proof {
+    pub fn from_slice(bytes: &[u8]) -> Result<ECVRF_Proof, ECVRF_Error> {
+        match bytes.len() {
+            80 => {
+                // format:
+                // 0                            32         48                         80
+                // |----------------------------|----------|---------------------------|
+                //      Gamma point               c scalar   s scalar
+                let gamma_opt = CompressedRistretto::from_slice(&bytes[0..32]).decompress();
+                if gamma_opt.is_none() {
+                    return Err(ECVRF_Error::InvalidDataError);
+                }
+
+                // reject the identity element -- a Gamma of the identity lets an
+                // attacker forge a "valid" proof for any public key
+                if gamma_opt.unwrap() == RistrettoPoint::identity() {
+                    return Err(ECVRF_Error::InvalidDataError);
+                }
+
+                let mut c_buf = [0u8; 32];
+                let mut s_buf = [0u8; 32];
+
+                c_buf[..16].copy_from_slice(&bytes[32..48]);
+                s_buf.copy_from_slice(&bytes[48..80]);
+
+                let c = ed25519_Scalar::from_bits(c_buf);
+                let s = ed25519_Scalar::from_bits(s_buf);
+
+                Ok(ECVRF_Proof {
+                    Gamma: gamma_opt.unwrap(),
+                    c,
+                    s
+                })
+            },
+            _ => Err(ECVRF_Error::InvalidDataError)
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<ECVRF_Proof, ECVRF_Error> {
+        ECVRF_Proof::from_slice(bytes)
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 80], ECVRF_Error> {
+        let mut c_bytes_16 = [0u8; 16];
+        let c_bytes = self.c.reduce().to_bytes();
+
+        // upper 16 bytes of c must be 0's
+        for i in 16..32 {
+            if c_bytes[i] != 0 {
+                return Err(ECVRF_Error::InvalidDataError);
+            }
+
+            c_bytes_16[i-16] = c_bytes[i-16];
+        }
+
+        let gamma_bytes = self.Gamma.compress().to_bytes();
+        let s_bytes = self.s.to_bytes();
+
+        let mut ret : Vec<u8> = Vec::with_capacity(80);
+        ret.extend_from_slice(&gamma_bytes);
+        ret.extend_from_slice(&c_bytes_16);
+        ret.extend_from_slice(&s_bytes);
+
+        let mut proof_bytes = [0u8; 80];
+        proof_bytes.copy_from_slice(&ret[..]);
+        Ok(proof_bytes)
+    }
+}
+
+
+pub fn ECVRF_point_to_string(p: &RistrettoPoint) -> Vec<u8> {
+    p.compress().as_bytes().to_vec()
+}
+
+pub fn ECVRF_hash_to_curve(y: &ECVRF_PublicKey, alpha: &[u8]) -> Result<RistrettoPoint, ECVRF_Error> {
+    let pk_bytes = y.to_bytes();
+
+    let mut hasher = Sha512::new();
+    let mut result = [0u8; 64];        // encodes 2 field elements from the hash
+
+    hasher.input([SUITE, 0x01]);
+    hasher.input(&pk_bytes[..]);
+    hasher.input(alpha);
+
+    let rs = &hasher.result()[..];
+    result.copy_from_slice(rs);
+
+    let h_point = RistrettoPoint::from_uniform_bytes(&result);
+    if h_point == RistrettoPoint::identity() {
+        return Err(ECVRF_Error::InvalidDataError);
+    }
+
+    Ok(h_point)
+}
+
+
+fn ECVRF_hash_points(p1: &RistrettoPoint, p2: &RistrettoPoint, p3: &RistrettoPoint, p4: &RistrettoPoint) -> [u8; 16] {
+    let mut hasher = Sha512::new();
+    let mut sha512_result = [0u8; 64];
+    let mut hash128 = [0u8; 16];
+
+    let p1_bytes = ECVRF_point_to_string(p1);
+    let p2_bytes = ECVRF_point_to_string(p2);
+    let p3_bytes = ECVRF_point_to_string(p3);
+    let p4_bytes = ECVRF_point_to_string(p4);
+
+    hasher.input([SUITE, 0x02]);
+    hasher.input(&p1_bytes[..]);
+    hasher.input(&p2_bytes[..]);
+    hasher.input(&p3_bytes[..]);
+    hasher.input(&p4_bytes[..]);
+
+    let rs = &hasher.result()[..];
+    sha512_result.copy_from_slice(rs);
+
+    hash128.copy_from_slice(&sha512_result[..16]);
+
+    hash128
+}
+
+fn ECVRF_expand_privkey(secret: &ECVRF_SecretKey) -> (ECVRF_PublicKey, ed25519_Scalar, [u8; 32]) {
+    let mut hasher = Sha512::new();
+    let mut h = [0u8; 64];
+    let mut trunc_hash = [0u8; 32];
+
+    // hash secret key to produce nonce and intermediate private key
+    hasher.input(&secret.seed[0..32]);
+    h.copy_from_slice(&hasher.result()[..]);
+
+    // h will encode a new private key, so we need to twiddle a few bits to make sure it falls in the
+    // right range (i.e. the curve order).
+    h[0] &= 248;
+    h[31] &= 127;
+    h[31] |= 64;
+
+    let mut h_32 = [0u8; 32];
+    h_32.copy_from_slice(&h[0..32]);
+
+    let x_scalar = ed25519_Scalar::from_bits(h_32);
+    trunc_hash.copy_from_slice(&h[32..64]);
+
+    // the public key is simply the same scalar multiplied against the
+    // Ristretto basepoint, exactly as Gamma is x_scalar * H_point
+    let pubkey = ECVRF_PublicKey { point: x_scalar * RISTRETTO_BASEPOINT_POINT };
+
+    h.zeroize();
+    h_32.zeroize();
+
+    (pubkey, x_scalar, trunc_hash)
+}
+
+fn ECVRF_nonce_generation(trunc_hash: &[u8; 32], H_point: &RistrettoPoint) -> ed25519_Scalar {
+    let mut hasher = Sha512::new();
+    let mut k_string = [0u8; 64];
+    let h_string = H_point.compress().to_bytes();
+
+    hasher.input(trunc_hash);
+    hasher.input(h_string);
+    let rs = &hasher.result()[..];
+    k_string.copy_from_slice(rs);
+
+    let mut k_32 = [0u8; 32];
+    k_32.copy_from_slice(&k_string[0..32]);
+
+    let k = ed25519_Scalar::from_bits(k_32);
+
+    k_string.zeroize();
+    k_32.zeroize();
+
+    k.reduce()
+}
+
+fn ECVRF_ed25519_scalar_from_hash128(hash128: &[u8; 16]) -> ed25519_Scalar {
+    let mut scalar_buf = [0u8; 32];
+    scalar_buf[..16].copy_from_slice(hash128);
+
+    ed25519_Scalar::from_bits(scalar_buf)
+}
+
+pub fn ECVRF_prove(secret: &ECVRF_SecretKey, alpha: &[u8]) -> Result<ECVRF_Proof, ECVRF_Error> {
+    let (Y_point, mut x_scalar, mut trunc_hash) = ECVRF_expand_privkey(secret);
+    let H_point = ECVRF_hash_to_curve(&Y_point, alpha)?;
+
+    let Gamma_point = x_scalar * H_point;
+    let mut k_scalar = ECVRF_nonce_generation(&trunc_hash, &H_point);
+
+    let kB_point = k_scalar * RISTRETTO_BASEPOINT_POINT;
+    let kH_point = k_scalar * H_point;
+
+    let c_hashbuf = ECVRF_hash_points(&H_point, &Gamma_point, &kB_point, &kH_point);
+    let c_scalar = ECVRF_ed25519_scalar_from_hash128(&c_hashbuf);
+
+    let s_full_scalar = c_scalar * x_scalar + k_scalar;
+    let s_scalar = s_full_scalar.reduce();
+
+    trunc_hash.zeroize();
+    x_scalar.zeroize();
+    k_scalar.zeroize();
+
+    Ok(ECVRF_Proof {
+        Gamma: Gamma_point,
+        c: c_scalar,
+        s: s_scalar
+    })
+}
+
+/// Derive the VRF output (`beta`) from a proof's Gamma point, per the
+/// ECVRF draft: beta = SHA512(SUITE || 0x03 || point_to_string(Gamma) || 0x00).
+/// The Ristretto group has cofactor 1, so no cofactor clearing is needed.
+pub fn ECVRF_proof_to_hash(proof: &ECVRF_Proof) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    let mut beta = [0u8; 64];
+
+    let gamma_bytes = ECVRF_point_to_string(&proof.Gamma);
+
+    hasher.input([SUITE, 0x03]);
+    hasher.input(&gamma_bytes[..]);
+    hasher.input([0x00]);
+
+    let rs = &hasher.result()[..];
+    beta.copy_from_slice(rs);
+    beta
+}
+
+/// Verify a proof and, only if it is valid, return the VRF output it attests to.
+pub fn ECVRF_verify_and_hash(Y_point: &ECVRF_PublicKey, proof: &ECVRF_Proof, alpha: &[u8]) -> Result<Option<[u8; 64]>, ECVRF_Error> {
+    let valid = ECVRF_verify(Y_point, proof, alpha)?;
+    if valid {
+        Ok(Some(ECVRF_proof_to_hash(proof)))
+    }
+    else {
+        Ok(None)
+    }
+}
+
+pub fn ECVRF_verify(Y_point: &ECVRF_PublicKey, proof: &ECVRF_Proof, alpha: &[u8]) -> Result<bool, ECVRF_Error> {
+    // reject an identity Gamma before doing any scalar math on it (Gamma is
+    // a RistrettoPoint, so it is always a valid group element however the
+    // proof was built; ECVRF_Proof::from_slice and ECVRF_PublicKey::from_bytes
+    // already reject the identity when decoding from bytes)
+    if proof.Gamma == RistrettoPoint::identity() {
+        return Err(ECVRF_Error::InvalidDataError);
+    }
+
+    let H_point = ECVRF_hash_to_curve(Y_point, alpha)?;
+    let s_reduced = proof.s.reduce();
+    let neg_c = -proof.c;
+
+    // verification only ever touches public data, so it's safe (and much
+    // faster) to use variable-time interleaved-window multiscalar
+    // multiplication here instead of two independent constant-time
+    // scalar multiplications per point
+    let U_point = RistrettoPoint::vartime_multiscalar_mul(
+        &[s_reduced, neg_c],
+        &[RISTRETTO_BASEPOINT_POINT, Y_point.point]
+    );
+    let V_point = RistrettoPoint::vartime_multiscalar_mul(
+        &[s_reduced, neg_c],
+        &[H_point, proof.Gamma]
+    );
+
+    let c_prime_hashbuf = ECVRF_hash_points(&H_point, &proof.Gamma, &U_point, &V_point);
+    let c_prime = ECVRF_ed25519_scalar_from_hash128(&c_prime_hashbuf);
+
+    // NOTE: this leverages constant-time comparison inherited from the Scalar impl
+    Ok(c_prime == proof.c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_and_hash_matches_proof_to_hash() {
+        let secret = ECVRF_SecretKey::new([0x07u8; 32]);
+        let pubkey = secret.public_key();
+        let alpha = b"hello ecvrf".to_vec();
+
+        let proof = ECVRF_prove(&secret, &alpha).unwrap();
+        let beta = ECVRF_verify_and_hash(&pubkey, &proof, &alpha).unwrap();
+        assert_eq!(beta, Some(ECVRF_proof_to_hash(&proof)));
+    }
+
+    #[test]
+    fn test_public_key_rejects_identity() {
+        let identity_bytes = [0u8; 32];
+        assert!(ECVRF_PublicKey::from_bytes(&identity_bytes).is_err());
+    }
+
+    #[test]
+    fn test_keygen_public_key_round_trip() {
+        let secret = ECVRF_SecretKey::new([0x42u8; 32]);
+        let pubkey_bytes = secret.public_key().to_bytes();
+        let pubkey = ECVRF_PublicKey::from_bytes(&pubkey_bytes).unwrap();
+        assert_eq!(pubkey_bytes, pubkey.to_bytes());
+    }
+
+    #[test]
+    fn test_prove_verify_round_trip() {
+        let secret = ECVRF_SecretKey::new([0x13u8; 32]);
+        let pubkey = secret.public_key();
+        let alpha = b"prove then verify".to_vec();
+        let other_alpha = b"a different message".to_vec();
+
+        let proof = ECVRF_prove(&secret, &alpha).unwrap();
+        assert!(ECVRF_verify(&pubkey, &proof, &alpha).unwrap());
+        assert!(!ECVRF_verify(&pubkey, &proof, &other_alpha).unwrap());
+    }
+
+    #[test]
+    fn test_proof_rejects_identity_gamma() {
+        // an all-zero Gamma decompresses to the Ristretto identity point
+        let proof_bytes = [0u8; 80];
+        assert!(ECVRF_Proof::from_slice(&proof_bytes).is_err());
+    }
+
+    #[test]
+    fn test_vartime_multiscalar_mul_matches_naive_formula() {
+        let secret = ECVRF_SecretKey::new([0x99u8; 32]);
+        let pubkey = secret.public_key();
+        let alpha = b"multiscalar check".to_vec();
+        let proof = ECVRF_prove(&secret, &alpha).unwrap();
+
+        let H_point = ECVRF_hash_to_curve(&pubkey, &alpha).unwrap();
+        let s_reduced = proof.s.reduce();
+        let neg_c = -proof.c;
+
+        // this is the U/V computation ECVRF_verify used before switching to
+        // vartime_multiscalar_mul; the two must agree exactly
+        let u_naive = s_reduced * RISTRETTO_BASEPOINT_POINT - proof.c * pubkey.point;
+        let v_naive = s_reduced * H_point - proof.c * proof.Gamma;
+
+        let u_fast = RistrettoPoint::vartime_multiscalar_mul(
+            &[s_reduced, neg_c],
+            &[RISTRETTO_BASEPOINT_POINT, pubkey.point]
+        );
+        let v_fast = RistrettoPoint::vartime_multiscalar_mul(
+            &[s_reduced, neg_c],
+            &[H_point, proof.Gamma]
+        );
+
+        assert_eq!(u_fast.compress(), u_naive.compress());
+        assert_eq!(v_fast.compress(), v_naive.compress());
+    }
+
+    #[test]
+    fn test_verify_and_hash_returns_none_on_bad_proof() {
+        let secret = ECVRF_SecretKey::new([0x07u8; 32]);
+        let pubkey = secret.public_key();
+        let alpha = b"hello ecvrf".to_vec();
+        let other_alpha = b"goodbye ecvrf".to_vec();
+
+        let proof = ECVRF_prove(&secret, &alpha).unwrap();
+        let beta = ECVRF_verify_and_hash(&pubkey, &proof, &other_alpha).unwrap();
+        assert_eq!(beta, None);
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(not(feature = "std"))]
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::error;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::format;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    InvalidDataError
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidDataError => write!(f, "Invalid data")
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidDataError => "Invalid data"
+        }
+    }
+}
+
+/// Decode a hex string into bytes.  Returns an error if the string is not
+/// an even-length string of hex digits -- including non-ASCII input, which
+/// would otherwise panic on a byte-index slice that lands mid-character.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn hex_bytes(s: &str) -> Result<Vec<u8>, Error> {
+    let bytes = s.as_bytes();
+    if bytes.len() % 2 != 0 || !bytes.iter().all(u8::is_ascii_hexdigit) {
+        return Err(Error::InvalidDataError);
+    }
+
+    let mut ret = Vec::with_capacity(bytes.len() / 2);
+    for chunk in bytes.chunks(2) {
+        // safe: every byte above was just confirmed to be an ASCII hex digit
+        let pair = core::str::from_utf8(chunk).map_err(|_e| Error::InvalidDataError)?;
+        let byte = u8::from_str_radix(pair, 16).map_err(|_e| Error::InvalidDataError)?;
+        ret.push(byte);
+    }
+
+    Ok(ret)
+}
+
+/// Encode bytes as a lowercase hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut ret = String::with_capacity(bytes.len() * 2);
+    for byte in bytes.iter() {
+        ret.push_str(&format!("{:02x}", byte));
+    }
+
+    ret
+}